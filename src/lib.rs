@@ -0,0 +1,4 @@
+pub mod memory;
+pub mod program;
+
+pub use program::workflow::Workflow;
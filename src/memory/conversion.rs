@@ -0,0 +1,221 @@
+use super::types::TypedValue;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+/// How a raw `Entry::String` should be coerced into a typed value before it's handed to a
+/// prompt or a conditional edge. Annotated in workflow JSON by name: `"int"`, `"float"`,
+/// `"bool"`, `"timestamp"`, `"timestamp_fmt:<strftime pattern>"`, or
+/// `"timestamp_fmt_tz:<strftime pattern>:<IANA timezone>"`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum Conversion {
+    Int,
+    Float,
+    Bool,
+    /// Parses an RFC 3339 timestamp.
+    Timestamp,
+    /// Parses with a custom strftime-style format string, e.g. `"%Y-%m-%d"`.
+    TimestampFmt(String),
+    /// Like `TimestampFmt`, but the parsed naive timestamp is localized to the given IANA
+    /// timezone (e.g. `"Europe/Istanbul"`) before being converted to UTC.
+    TimestampFmtTz(String, String),
+}
+
+/// Raised when a `Conversion` cannot coerce the entry it was given.
+#[derive(Debug, thiserror::Error)]
+pub enum ConversionError {
+    #[error("memory key `{key}` holds a non-string entry, which cannot be converted")]
+    NotAString { key: String },
+    #[error("could not parse `{raw}` as {target} for key `{key}`: {reason}")]
+    Parse {
+        key: String,
+        raw: String,
+        target: &'static str,
+        reason: String,
+    },
+}
+
+impl Conversion {
+    /// Parses a conversion name as it appears in workflow JSON: `"int"`, `"float"`, `"bool"`,
+    /// `"timestamp"`, `"timestamp_fmt:<strftime pattern>"`, or
+    /// `"timestamp_fmt_tz:<strftime pattern>:<IANA timezone>"`. The timezone-aware form splits on
+    /// the *last* `:`, since an IANA zone name (e.g. `Europe/Istanbul`) never contains one but a
+    /// strftime pattern (e.g. `%H:%M:%S`) often does.
+    pub fn parse(name: &str) -> Option<Self> {
+        if let Some(rest) = name.strip_prefix("timestamp_fmt_tz:") {
+            let (fmt, tz) = rest.rsplit_once(':')?;
+            return Some(Conversion::TimestampFmtTz(fmt.to_string(), tz.to_string()));
+        }
+        if let Some(fmt) = name.strip_prefix("timestamp_fmt:") {
+            return Some(Conversion::TimestampFmt(fmt.to_string()));
+        }
+        match name {
+            "int" => Some(Conversion::Int),
+            "float" => Some(Conversion::Float),
+            "bool" => Some(Conversion::Bool),
+            "timestamp" => Some(Conversion::Timestamp),
+            _ => None,
+        }
+    }
+
+    /// Coerces `raw` (the string content of the memory entry at `key`) into a `TypedValue`.
+    pub fn apply(&self, key: &str, raw: &str) -> Result<TypedValue, ConversionError> {
+        // A date-only format (e.g. `%Y-%m-%d`) has no time fields for `NaiveDateTime` to parse;
+        // such a value is read as midnight of that date.
+        let parse_naive = |fmt: &str| {
+            NaiveDateTime::parse_from_str(raw, fmt).or_else(|err| {
+                NaiveDate::parse_from_str(raw, fmt)
+                    .map(|date| date.and_hms_opt(0, 0, 0).expect("midnight is a valid time"))
+                    .map_err(|_| err)
+            })
+        };
+        match self {
+            Conversion::Int => raw
+                .parse::<i64>()
+                .map(TypedValue::Int)
+                .map_err(|e| Self::parse_err(key, raw, "int", e)),
+            Conversion::Float => raw
+                .parse::<f64>()
+                .map(TypedValue::Float)
+                .map_err(|e| Self::parse_err(key, raw, "float", e)),
+            Conversion::Bool => raw
+                .parse::<bool>()
+                .map(TypedValue::Bool)
+                .map_err(|e| Self::parse_err(key, raw, "bool", e)),
+            Conversion::Timestamp => DateTime::parse_from_rfc3339(raw)
+                .map(|dt| TypedValue::Timestamp(dt.with_timezone(&Utc)))
+                .map_err(|e| Self::parse_err(key, raw, "timestamp", e)),
+            Conversion::TimestampFmt(fmt) => parse_naive(fmt)
+                .map(|naive| TypedValue::Timestamp(Utc.from_utc_datetime(&naive)))
+                .map_err(|e| Self::parse_err(key, raw, "timestamp_fmt", e)),
+            Conversion::TimestampFmtTz(fmt, tz) => {
+                let naive = parse_naive(fmt)
+                    .map_err(|e| Self::parse_err(key, raw, "timestamp_fmt_tz", e))?;
+                let zone: chrono_tz::Tz = tz.parse().map_err(|_| ConversionError::Parse {
+                    key: key.to_string(),
+                    raw: raw.to_string(),
+                    target: "timestamp_fmt_tz",
+                    reason: format!("unknown timezone `{tz}`"),
+                })?;
+                let localized = zone.from_local_datetime(&naive).single().ok_or_else(|| {
+                    ConversionError::Parse {
+                        key: key.to_string(),
+                        raw: raw.to_string(),
+                        target: "timestamp_fmt_tz",
+                        reason: "ambiguous or nonexistent local time".to_string(),
+                    }
+                })?;
+                Ok(TypedValue::Timestamp(localized.with_timezone(&Utc)))
+            }
+        }
+    }
+
+    fn parse_err(
+        key: &str,
+        raw: &str,
+        target: &'static str,
+        reason: impl std::fmt::Display,
+    ) -> ConversionError {
+        ConversionError::Parse {
+            key: key.to_string(),
+            raw: raw.to_string(),
+            target,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_applies_int() {
+        let conversion = Conversion::parse("int").expect("recognized");
+        assert!(matches!(conversion, Conversion::Int));
+        assert!(matches!(
+            conversion.apply("k", "42").unwrap(),
+            TypedValue::Int(42)
+        ));
+    }
+
+    #[test]
+    fn parses_and_applies_float() {
+        let conversion = Conversion::parse("float").expect("recognized");
+        assert!(matches!(conversion, Conversion::Float));
+        match conversion.apply("k", "3.5").unwrap() {
+            TypedValue::Float(f) => assert_eq!(f, 3.5),
+            other => panic!("expected Float, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_and_applies_bool() {
+        let conversion = Conversion::parse("bool").expect("recognized");
+        assert!(matches!(conversion, Conversion::Bool));
+        assert!(matches!(
+            conversion.apply("k", "true").unwrap(),
+            TypedValue::Bool(true)
+        ));
+    }
+
+    #[test]
+    fn parses_and_applies_timestamp() {
+        let conversion = Conversion::parse("timestamp").expect("recognized");
+        assert!(matches!(conversion, Conversion::Timestamp));
+        let value = conversion.apply("k", "2024-01-02T03:04:05Z").unwrap();
+        assert!(matches!(value, TypedValue::Timestamp(_)));
+    }
+
+    #[test]
+    fn parses_and_applies_timestamp_fmt() {
+        let conversion = Conversion::parse("timestamp_fmt:%Y-%m-%d").expect("recognized");
+        assert!(matches!(conversion, Conversion::TimestampFmt(ref fmt) if fmt == "%Y-%m-%d"));
+        let value = conversion.apply("k", "2024-01-02").unwrap();
+        assert!(matches!(value, TypedValue::Timestamp(_)));
+    }
+
+    #[test]
+    fn parses_and_applies_timestamp_fmt_tz() {
+        let conversion = Conversion::parse("timestamp_fmt_tz:%Y-%m-%d %H:%M:%S:Europe/Istanbul")
+            .expect("recognized");
+        assert!(matches!(
+            conversion,
+            Conversion::TimestampFmtTz(ref fmt, ref tz)
+                if fmt == "%Y-%m-%d %H:%M:%S" && tz == "Europe/Istanbul"
+        ));
+        let value = conversion.apply("k", "2024-01-02 03:04:05").unwrap();
+        assert!(matches!(value, TypedValue::Timestamp(_)));
+    }
+
+    #[test]
+    fn parse_rejects_unknown_name() {
+        assert!(Conversion::parse("not_a_conversion").is_none());
+    }
+
+    #[test]
+    fn apply_reports_bad_int() {
+        let conversion = Conversion::parse("int").unwrap();
+        let err = conversion.apply("k", "not a number").unwrap_err();
+        match err {
+            ConversionError::Parse { target, .. } => assert_eq!(target, "int"),
+            other => panic!("expected Parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn apply_reports_ambiguous_local_time() {
+        // US DST fall-back in 2023: clocks turn back from 02:00 to 01:00 on Nov 5, so 01:30
+        // local occurs twice and is ambiguous.
+        let conversion =
+            Conversion::parse("timestamp_fmt_tz:%Y-%m-%d %H:%M:%S:America/New_York").unwrap();
+        let err = conversion
+            .apply("k", "2023-11-05 01:30:00")
+            .unwrap_err();
+        match err {
+            ConversionError::Parse { target, reason, .. } => {
+                assert_eq!(target, "timestamp_fmt_tz");
+                assert!(reason.contains("ambiguous") || reason.contains("nonexistent"));
+            }
+            other => panic!("expected Parse error, got {other:?}"),
+        }
+    }
+}
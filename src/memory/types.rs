@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+/// Identifier used to address a memory page or entry (e.g. a task id or a memory key).
+pub type ID = String;
+
+/// A value that has been coerced out of a raw `Entry::String` via a `Conversion`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum TypedValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Timestamp(DateTime<Utc>),
+}
+
+/// A single value stored in the workflow's external memory.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum Entry {
+    String(String),
+    Json(Value),
+    /// Produced at load time when a memory key is annotated with a `Conversion`.
+    Typed(TypedValue),
+}
+
+/// The shape under which a memory key was provided: a single entry, or a stack page of entries.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum MemoryInputType {
+    Entry(Entry),
+    Page(Vec<Entry>),
+}
@@ -0,0 +1,72 @@
+use crate::memory::types::{Entry, ID};
+use serde::{Deserialize, Serialize};
+
+/// Configuration shared by a workflow and its tasks: which models may be used and execution limits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    pub models: Vec<String>,
+    #[serde(default)]
+    pub max_steps: Option<usize>,
+    #[serde(default)]
+    pub max_time: Option<u64>,
+    /// Fuel budget given to a `Wasm` task's guest instance; `None` disables metering.
+    #[serde(default)]
+    pub wasm_fuel_limit: Option<u64>,
+    /// Wall-clock budget in milliseconds given to a `Wasm` task's guest instance.
+    #[serde(default)]
+    pub wasm_time_limit_ms: Option<u64>,
+    /// Maximum number of tasks `Workflow::run_parallel` will execute at once; `None` runs an
+    /// entire independent layer concurrently with no cap.
+    #[serde(default)]
+    pub max_parallelism: Option<usize>,
+}
+
+/// Where a WASM module's bytecode comes from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WasmSource {
+    /// Path to a `.wasm`/`.wat` file on disk, resolved relative to the process' working directory.
+    Path(String),
+    /// Inline module bytecode, e.g. embedded at build time or fetched ahead of execution.
+    Bytes(Vec<u8>),
+}
+
+/// The operation a task performs when it executes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "operator", rename_all = "snake_case")]
+pub enum Operator {
+    Generation { model: String, prompt: String },
+    Search { model: String, prompt: String },
+    Function { name: String, params: serde_json::Value },
+    /// Runs a guest WASM module instead of (or alongside) an LLM call. The module is invoked at
+    /// `entrypoint` with the task's resolved inputs as JSON and must return JSON back.
+    Wasm {
+        module: WasmSource,
+        entrypoint: String,
+    },
+}
+
+/// A single unit of work in a workflow: an id, the operation it runs, and the inputs it reads.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub id: String,
+    pub operator: Operator,
+    /// Memory keys (or upstream task ids) this task reads its inputs from.
+    #[serde(default)]
+    pub inputs: Vec<ID>,
+}
+
+/// A directed edge connecting the output of one task to the next step in the workflow.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Edge {
+    pub source: String,
+    pub target: String,
+    #[serde(default)]
+    pub condition: Option<String>,
+}
+
+/// The result produced by executing a task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskOutput {
+    pub task_id: String,
+    pub result: Entry,
+}
@@ -0,0 +1,99 @@
+use super::atomics::{Operator, Task, TaskOutput};
+use crate::memory::types::{Entry, MemoryInputType, ID};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Stable content hash over everything that can affect a task's output.
+pub type TaskHash = u64;
+
+/// Pluggable storage for cached task outputs, keyed by `TaskHash`.
+///
+/// The default implementation is an in-memory `HashMap`; a file-backed JSON or sqlite store
+/// can be dropped in by implementing this trait and passing it to `Workflow::with_cache`.
+pub trait TaskCache: std::fmt::Debug {
+    fn get(&self, hash: TaskHash) -> Option<TaskOutput>;
+    fn insert(&mut self, hash: TaskHash, output: TaskOutput);
+    fn clear(&mut self);
+}
+
+/// Default in-memory cache. Entries are lost when the workflow is dropped.
+#[derive(Debug, Default)]
+pub struct InMemoryTaskCache {
+    entries: HashMap<TaskHash, TaskOutput>,
+}
+
+impl InMemoryTaskCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TaskCache for InMemoryTaskCache {
+    fn get(&self, hash: TaskHash) -> Option<TaskOutput> {
+        self.entries.get(&hash).cloned()
+    }
+
+    fn insert(&mut self, hash: TaskHash, output: TaskOutput) {
+        self.entries.insert(hash, output);
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Computes the content hash for a task from its config-relevant fields (the operator, which
+/// carries the model and prompt body) and the fully-resolved inputs it reads from memory.
+///
+/// Folding in the resolved input values rather than just their keys is what makes the cache
+/// safe: if an upstream task's output changes, the hash changes with it, so a stale answer
+/// can never leak across edits.
+pub fn hash_task(task: &Task, resolved_inputs: &[(&ID, &MemoryInputType)]) -> TaskHash {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    task.id.hash(&mut hasher);
+    hash_operator(&task.operator, &mut hasher);
+    for (key, value) in resolved_inputs {
+        key.hash(&mut hasher);
+        hash_memory_input(value, &mut hasher);
+    }
+    hasher.finish()
+}
+
+fn hash_operator(operator: &Operator, hasher: &mut impl Hasher) {
+    if let Ok(json) = serde_json::to_string(operator) {
+        json.hash(hasher);
+    }
+}
+
+fn hash_memory_input(value: &MemoryInputType, hasher: &mut impl Hasher) {
+    if let Ok(json) = serde_json::to_string(&memory_input_as_entries(value)) {
+        json.hash(hasher);
+    }
+}
+
+fn memory_input_as_entries(value: &MemoryInputType) -> Vec<&Entry> {
+    match value {
+        MemoryInputType::Entry(entry) => vec![entry],
+        MemoryInputType::Page(page) => page.iter().collect(),
+    }
+}
+
+/// Whether a task's output came from the cache or was freshly computed, so callers can tell a
+/// cache hit happened instead of the two cases being indistinguishable.
+#[derive(Debug, Clone)]
+pub enum CacheOutcome {
+    Hit(TaskOutput),
+    Miss(TaskOutput),
+}
+
+impl CacheOutcome {
+    pub fn is_hit(&self) -> bool {
+        matches!(self, CacheOutcome::Hit(_))
+    }
+
+    pub fn into_output(self) -> TaskOutput {
+        match self {
+            CacheOutcome::Hit(output) | CacheOutcome::Miss(output) => output,
+        }
+    }
+}
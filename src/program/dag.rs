@@ -0,0 +1,320 @@
+use super::atomics::{Edge, Task, TaskOutput};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Condvar, Mutex};
+
+/// One task in the resolved execution plan, alongside the upstream task ids it must wait on.
+#[derive(Debug, Clone)]
+pub struct PlanNode {
+    pub task_id: String,
+    pub depends_on: Vec<String>,
+}
+
+/// A topologically-sorted execution plan. `layers` groups task ids that have no data dependency
+/// on one another -- every task in a layer only depends on tasks in earlier layers -- so each
+/// layer can be executed concurrently.
+#[derive(Debug, Clone)]
+pub struct ExecutionPlan {
+    pub order: Vec<PlanNode>,
+    pub layers: Vec<Vec<String>>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DagError {
+    #[error("edge references unknown task id `{0}`")]
+    UnknownTask(String),
+    #[error("workflow graph has a cycle involving tasks: {0:?}")]
+    Cycle(Vec<String>),
+}
+
+/// Builds the dependency graph from `steps` (and each task's declared `inputs`), detects cycles,
+/// and returns a topologically sorted `ExecutionPlan`.
+pub fn resolve(tasks: &[Task], steps: &[Edge]) -> Result<ExecutionPlan, DagError> {
+    let task_ids: HashSet<&str> = tasks.iter().map(|task| task.id.as_str()).collect();
+
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut depends_on: HashMap<&str, Vec<&str>> = HashMap::new();
+    for task in tasks {
+        dependents.entry(task.id.as_str()).or_default();
+        depends_on.entry(task.id.as_str()).or_default();
+    }
+
+    for edge in steps {
+        if !task_ids.contains(edge.source.as_str()) {
+            return Err(DagError::UnknownTask(edge.source.clone()));
+        }
+        if !task_ids.contains(edge.target.as_str()) {
+            return Err(DagError::UnknownTask(edge.target.clone()));
+        }
+        dependents
+            .entry(edge.source.as_str())
+            .or_default()
+            .push(edge.target.as_str());
+        depends_on
+            .entry(edge.target.as_str())
+            .or_default()
+            .push(edge.source.as_str());
+    }
+
+    // A task also implicitly depends on any upstream task it reads via `inputs`, beyond what
+    // `steps` encodes, so fan-out branches that only communicate through memory still order
+    // correctly.
+    for task in tasks {
+        for input in &task.inputs {
+            if input != &task.id && task_ids.contains(input.as_str()) {
+                depends_on
+                    .entry(task.id.as_str())
+                    .or_default()
+                    .push(input.as_str());
+                dependents
+                    .entry(input.as_str())
+                    .or_default()
+                    .push(task.id.as_str());
+            }
+        }
+    }
+
+    let mut in_degree: HashMap<&str, usize> = task_ids
+        .iter()
+        .map(|id| (*id, depends_on.get(id).map_or(0, Vec::len)))
+        .collect();
+
+    let mut ready: VecDeque<&str> = in_degree
+        .iter()
+        .filter(|(_, degree)| **degree == 0)
+        .map(|(id, _)| *id)
+        .collect();
+
+    let mut order = Vec::new();
+    let mut layers = Vec::new();
+    let mut visited = 0;
+
+    while !ready.is_empty() {
+        let mut layer: Vec<&str> = ready.drain(..).collect();
+        layer.sort_unstable(); // deterministic regardless of HashMap iteration order
+
+        for &id in &layer {
+            visited += 1;
+            order.push(PlanNode {
+                task_id: id.to_string(),
+                depends_on: depends_on
+                    .get(id)
+                    .cloned()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+            });
+            for &dependent in dependents.get(id).into_iter().flatten() {
+                let degree = in_degree.get_mut(dependent).expect("task id was registered above");
+                *degree -= 1;
+                if *degree == 0 {
+                    ready.push_back(dependent);
+                }
+            }
+        }
+
+        layers.push(layer.into_iter().map(String::from).collect());
+    }
+
+    if visited != task_ids.len() {
+        let mut unresolved: HashSet<&str> = in_degree
+            .into_iter()
+            .filter(|(_, degree)| *degree > 0)
+            .map(|(id, _)| id)
+            .collect();
+        // Tasks merely downstream of a cycle are unresolved too; peel them off (they have no
+        // dependent left in the set) so only tasks on a cycle are reported.
+        loop {
+            let sinks: Vec<&str> = unresolved
+                .iter()
+                .copied()
+                .filter(|id| !dependents.get(id).into_iter().flatten().any(|d| unresolved.contains(d)))
+                .collect();
+            if sinks.is_empty() {
+                break;
+            }
+            for id in sinks {
+                unresolved.remove(id);
+            }
+        }
+        return Err(DagError::Cycle(unresolved.into_iter().map(String::from).collect()));
+    }
+
+    Ok(ExecutionPlan { order, layers })
+}
+
+/// A jobserver-style counting semaphore: `acquire` blocks until a token is free, capping how
+/// many tasks the scheduler runs at once.
+#[derive(Clone)]
+pub struct TokenPool {
+    state: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl TokenPool {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Arc::new((Mutex::new(capacity.max(1)), Condvar::new())),
+        }
+    }
+
+    pub fn acquire(&self) -> TokenGuard<'_> {
+        let (lock, cvar) = &*self.state;
+        let mut available = lock.lock().expect("token pool mutex poisoned");
+        while *available == 0 {
+            available = cvar.wait(available).expect("token pool mutex poisoned");
+        }
+        *available -= 1;
+        TokenGuard { pool: self }
+    }
+}
+
+pub struct TokenGuard<'a> {
+    pool: &'a TokenPool,
+}
+
+impl Drop for TokenGuard<'_> {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*self.pool.state;
+        *lock.lock().expect("token pool mutex poisoned") += 1;
+        cvar.notify_one();
+    }
+}
+
+/// Executes `plan` layer by layer: every task in a layer runs concurrently (bounded by
+/// `max_parallelism`), and a layer only starts once the previous one has fully completed, so a
+/// task never starts before every one of its graph predecessors has produced its output.
+///
+/// `execute` is handed the task and the outputs of every task completed in an earlier layer (read
+/// -only while the current layer runs), so a dependent task can actually consume the results it
+/// joins on -- the whole point of resolving the graph in the first place.
+pub fn run_parallel(
+    plan: &ExecutionPlan,
+    tasks: &[Task],
+    max_parallelism: Option<usize>,
+    execute: impl Fn(&Task, &HashMap<String, TaskOutput>) -> TaskOutput + Sync,
+) -> HashMap<String, TaskOutput> {
+    let pool = TokenPool::new(max_parallelism.unwrap_or(tasks.len().max(1)));
+    let tasks_by_id: HashMap<&str, &Task> =
+        tasks.iter().map(|task| (task.id.as_str(), task)).collect();
+    let mut outputs = HashMap::new();
+
+    for layer in &plan.layers {
+        let layer_results = std::thread::scope(|scope| {
+            let handles: Vec<_> = layer
+                .iter()
+                .filter_map(|task_id| tasks_by_id.get(task_id.as_str()).map(|task| (task_id, *task)))
+                .map(|(task_id, task)| {
+                    let pool = pool.clone();
+                    let execute = &execute;
+                    let completed = &outputs;
+                    scope.spawn(move || {
+                        let _token = pool.acquire();
+                        (task_id.clone(), execute(task, completed))
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("task thread panicked"))
+                .collect::<Vec<_>>()
+        });
+        outputs.extend(layer_results);
+    }
+
+    outputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::types::Entry;
+    use crate::program::atomics::Operator;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    fn task(id: &str, inputs: &[&str]) -> Task {
+        Task {
+            id: id.to_string(),
+            operator: Operator::Function {
+                name: "noop".to_string(),
+                params: serde_json::Value::Null,
+            },
+            inputs: inputs.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn edge(source: &str, target: &str) -> Edge {
+        Edge {
+            source: source.to_string(),
+            target: target.to_string(),
+            condition: None,
+        }
+    }
+
+    #[test]
+    fn resolve_reports_cycle_with_offending_ids() {
+        let tasks = vec![task("a", &[]), task("b", &[]), task("c", &[])];
+        let steps = vec![edge("a", "b"), edge("b", "c"), edge("c", "a")];
+
+        let err = resolve(&tasks, &steps).expect_err("graph has a cycle");
+        let DagError::Cycle(mut offenders) = err else {
+            panic!("expected DagError::Cycle, got {err:?}");
+        };
+        offenders.sort_unstable();
+        assert_eq!(offenders, vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+    }
+
+    #[test]
+    fn resolve_groups_independent_branches_into_the_same_layer() {
+        // root -> {left, right} -> join: left and right don't depend on each other, so they
+        // should land in the same layer even though join waits on both.
+        let tasks = vec![
+            task("root", &[]),
+            task("left", &[]),
+            task("right", &[]),
+            task("join", &[]),
+        ];
+        let steps = vec![
+            edge("root", "left"),
+            edge("root", "right"),
+            edge("left", "join"),
+            edge("right", "join"),
+        ];
+
+        let plan = resolve(&tasks, &steps).expect("graph is acyclic");
+        assert_eq!(plan.layers.len(), 3);
+        assert_eq!(plan.layers[0], vec!["root".to_string()]);
+        assert_eq!(
+            plan.layers[1],
+            vec!["left".to_string(), "right".to_string()]
+        );
+        assert_eq!(plan.layers[2], vec!["join".to_string()]);
+    }
+
+    #[test]
+    fn run_parallel_respects_max_parallelism() {
+        let tasks: Vec<Task> = (0..6).map(|i| task(&format!("t{i}"), &[])).collect();
+        let plan = resolve(&tasks, &[]).expect("graph is acyclic");
+
+        let in_flight = AtomicUsize::new(0);
+        let max_observed = AtomicUsize::new(0);
+
+        let outputs = run_parallel(&plan, &tasks, Some(2), |task, _completed| {
+            let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            max_observed.fetch_max(current, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(20));
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            TaskOutput {
+                task_id: task.id.clone(),
+                result: Entry::String("done".to_string()),
+            }
+        });
+
+        assert_eq!(outputs.len(), 6);
+        assert!(
+            max_observed.load(Ordering::SeqCst) <= 2,
+            "max_parallelism of 2 was exceeded: {}",
+            max_observed.load(Ordering::SeqCst)
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use thiserror::Error;
+
+/// Structured error type for workflow loading and execution.
+///
+/// Each variant except `ConfigError` carries context (a task id, a memory key, or what was being
+/// parsed) to pinpoint the fault; `ConfigError` carries only a description. Every variant maps to a short machine-readable class via `class()` so callers
+/// embedding the crate can act on a failure programmatically instead of matching an opaque
+/// boxed string.
+#[derive(Debug, Error)]
+pub enum WorkflowError {
+    #[error("failed to parse {context}: {reason}")]
+    ParseError { context: String, reason: String },
+    #[error("could not resolve task `{task_id}`: {detail}")]
+    ResolutionError { task_id: String, detail: String },
+    #[error("memory key `{key}`: {detail}")]
+    MemoryError { key: String, detail: String },
+    #[error("task `{task_id}` failed to execute: {detail}")]
+    ExecutionError { task_id: String, detail: String },
+    #[error("invalid config: {detail}")]
+    ConfigError { detail: String },
+}
+
+impl WorkflowError {
+    /// Short machine-readable class string, stable across error message wording changes.
+    pub fn class(&self) -> &'static str {
+        match self {
+            WorkflowError::ParseError { .. } => "parse_error",
+            WorkflowError::ResolutionError { .. } => "resolution_error",
+            WorkflowError::MemoryError { .. } => "memory_error",
+            WorkflowError::ExecutionError { .. } => "execution_error",
+            WorkflowError::ConfigError { .. } => "config_error",
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod atomics;
+pub mod cache;
+pub mod dag;
+pub mod error;
+pub mod snapshot;
+pub mod wasm;
+pub mod workflow;
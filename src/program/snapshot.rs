@@ -0,0 +1,123 @@
+use super::atomics::{Config, Edge, Task, TaskOutput};
+use crate::memory::types::{MemoryInputType, ID};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use tar::{Archive, Builder, Header};
+
+/// Bumped whenever the manifest shape changes; `read_archive` rejects manifests newer than this.
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// Everything needed to resume a workflow, minus the external memory (packed as its own tar
+/// entry, `external_memory.json`, in the same gzip stream).
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub config: Config,
+    pub tasks: Vec<Task>,
+    pub steps: Vec<Edge>,
+    pub return_value: TaskOutput,
+    /// Snapshot of task ids at dump time, used by `restore` to detect a workflow whose task set
+    /// has since changed.
+    pub task_ids: Vec<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("io error while {0}: {1}")]
+    Io(&'static str, #[source] std::io::Error),
+    #[error("archive is missing its manifest.json entry")]
+    MissingManifest,
+    #[error("archive manifest is version {found}, this build only reads up to version {MANIFEST_VERSION}")]
+    UnsupportedVersion { found: u32 },
+    #[error("archive's task set does not match the workflow being restored into")]
+    TaskSetMismatch,
+    #[error("failed to (de)serialize manifest or memory: {0}")]
+    Serde(#[from] serde_json::Error),
+}
+
+/// Writes `manifest` and `external_memory` into a gzip-compressed tar archive at `path`.
+pub fn write_archive(
+    path: impl AsRef<Path>,
+    manifest: &Manifest,
+    external_memory: &Option<HashMap<ID, MemoryInputType>>,
+) -> Result<(), SnapshotError> {
+    let file =
+        std::fs::File::create(path.as_ref()).map_err(|e| SnapshotError::Io("creating archive", e))?;
+    let encoder = GzEncoder::new(file, Compression::default());
+    let mut builder = Builder::new(encoder);
+
+    append_json(&mut builder, "manifest.json", manifest)?;
+    append_json(&mut builder, "external_memory.json", external_memory)?;
+
+    builder
+        .into_inner()
+        .map_err(|e| SnapshotError::Io("finishing archive", e))?
+        .finish()
+        .map_err(|e| SnapshotError::Io("flushing archive", e))?;
+    Ok(())
+}
+
+/// Reads back an archive written by `write_archive`, validating the manifest version before the
+/// caller gets a chance to check the task set against its own workflow.
+pub fn read_archive(
+    path: impl AsRef<Path>,
+) -> Result<(Manifest, Option<HashMap<ID, MemoryInputType>>), SnapshotError> {
+    let file =
+        std::fs::File::open(path.as_ref()).map_err(|e| SnapshotError::Io("opening archive", e))?;
+    let decoder = GzDecoder::new(file);
+    let mut archive = Archive::new(decoder);
+
+    let mut manifest: Option<Manifest> = None;
+    let mut external_memory: Option<HashMap<ID, MemoryInputType>> = None;
+
+    for entry in archive
+        .entries()
+        .map_err(|e| SnapshotError::Io("reading archive entries", e))?
+    {
+        let mut entry = entry.map_err(|e| SnapshotError::Io("reading archive entry", e))?;
+        let name = entry
+            .path()
+            .map_err(|e| SnapshotError::Io("reading entry path", e))?
+            .to_string_lossy()
+            .into_owned();
+
+        let mut contents = String::new();
+        entry
+            .read_to_string(&mut contents)
+            .map_err(|e| SnapshotError::Io("reading entry contents", e))?;
+
+        match name.as_str() {
+            "manifest.json" => manifest = Some(serde_json::from_str(&contents)?),
+            "external_memory.json" => external_memory = Some(serde_json::from_str(&contents)?),
+            _ => {}
+        }
+    }
+
+    let manifest = manifest.ok_or(SnapshotError::MissingManifest)?;
+    if manifest.version > MANIFEST_VERSION {
+        return Err(SnapshotError::UnsupportedVersion {
+            found: manifest.version,
+        });
+    }
+
+    Ok((manifest, external_memory))
+}
+
+fn append_json<W: Write>(
+    builder: &mut Builder<W>,
+    name: &str,
+    value: &impl serde::Serialize,
+) -> Result<(), SnapshotError> {
+    let bytes = serde_json::to_vec_pretty(value)?;
+    let mut header = Header::new_gnu();
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, name, bytes.as_slice())
+        .map_err(|e| SnapshotError::Io("writing archive entry", e))
+}
@@ -0,0 +1,278 @@
+use super::atomics::{Config, Task, TaskOutput, WasmSource};
+use crate::memory::types::{Entry, MemoryInputType, ID};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+use wasmtime::{Engine, Instance, Linker, Memory, Module, Store};
+
+/// Errors raised while instantiating or running a `Wasm` task.
+#[derive(Debug, thiserror::Error)]
+pub enum WasmExecutionError {
+    #[error("failed to read wasm module at `{path}`: {source}")]
+    ModuleRead {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to compile wasm module: {0}")]
+    Compile(#[source] wasmtime::Error),
+    #[error("entrypoint `{0}` not found or not exported as a function")]
+    MissingEntrypoint(String),
+    #[error("guest module trapped or ran out of fuel/time: {0}")]
+    Trap(#[source] wasmtime::Error),
+    #[error("guest returned output that is not valid JSON: {0}")]
+    InvalidOutput(#[source] serde_json::Error),
+}
+
+/// Host state made available to the guest through host functions: the task's resolved inputs,
+/// readable by key, and a set of stack pages the guest can append intermediate results to.
+struct HostState {
+    memory_entries: HashMap<ID, Entry>,
+    stack_pages: HashMap<ID, Vec<Entry>>,
+}
+
+/// A timer bound to the lifetime of a single `Wasm` call: it increments `engine`'s epoch if
+/// `timeout_ms` elapses, but cancels and joins itself as soon as it's dropped, so a call that
+/// finishes well under its deadline doesn't leave a thread sleeping out the rest of it.
+struct EpochTimer {
+    cancel: Option<std::sync::mpsc::Sender<()>>,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl EpochTimer {
+    fn spawn(engine: Engine, timeout_ms: u64) -> Self {
+        let (cancel_tx, cancel_rx) = std::sync::mpsc::channel();
+        let handle = std::thread::spawn(move || {
+            if cancel_rx.recv_timeout(Duration::from_millis(timeout_ms)).is_err() {
+                engine.increment_epoch();
+            }
+        });
+        EpochTimer {
+            cancel: Some(cancel_tx),
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for EpochTimer {
+    fn drop(&mut self) {
+        if let Some(cancel) = self.cancel.take() {
+            let _ = cancel.send(());
+        }
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Runs `task`'s `Wasm` operator, passing `resolved_inputs` to the guest as JSON and marshaling
+/// its JSON response back into a `TaskOutput`, alongside any stack pages the guest appended to
+/// via `host_push_page`.
+///
+/// The guest is instantiated fresh for each call and metered with the fuel/time limits from
+/// `config`, so a misbehaving module can only ever hang or burn resources up to that budget.
+pub fn execute_wasm_task(
+    task: &Task,
+    module: &WasmSource,
+    entrypoint: &str,
+    resolved_inputs: HashMap<ID, MemoryInputType>,
+    config: &Config,
+) -> Result<(TaskOutput, HashMap<ID, Vec<Entry>>), WasmExecutionError> {
+    let mut engine_config = wasmtime::Config::new();
+    if config.wasm_fuel_limit.is_some() {
+        engine_config.consume_fuel(true);
+    }
+    if config.wasm_time_limit_ms.is_some() {
+        // Without this, `Store::set_epoch_deadline`/`Engine::increment_epoch` below are no-ops:
+        // Wasmtime only compiles in the epoch checks that make a deadline actually interrupt a
+        // running guest when epoch interruption is enabled up front.
+        engine_config.epoch_interruption(true);
+    }
+    let engine = Engine::new(&engine_config).map_err(WasmExecutionError::Compile)?;
+
+    let bytes = load_module_bytes(module)?;
+    let wasm_module = Module::new(&engine, bytes).map_err(WasmExecutionError::Compile)?;
+
+    let memory_entries = flatten_inputs(resolved_inputs);
+    let mut store = Store::new(
+        &engine,
+        HostState {
+            memory_entries,
+            stack_pages: HashMap::new(),
+        },
+    );
+    if let Some(fuel) = config.wasm_fuel_limit {
+        store.set_fuel(fuel).map_err(WasmExecutionError::Trap)?;
+    }
+    // Dropped (and so cancelled/joined) at the end of this function, however it returns.
+    let _epoch_timer = config
+        .wasm_time_limit_ms
+        .map(|timeout_ms| EpochTimer::spawn(engine.clone(), timeout_ms));
+    if config.wasm_time_limit_ms.is_some() {
+        store.set_epoch_deadline(1);
+    }
+
+    let mut linker = Linker::new(&engine);
+    link_host_functions(&mut linker).map_err(WasmExecutionError::Compile)?;
+
+    let instance = linker
+        .instantiate(&mut store, &wasm_module)
+        .map_err(WasmExecutionError::Trap)?;
+
+    let input_json = serde_json::to_vec(&host_visible_inputs(&store.data().memory_entries))
+        .expect("memory entries are always serializable");
+    let output_json = call_entrypoint(&mut store, &instance, entrypoint, &input_json)?;
+
+    let result: Value =
+        serde_json::from_slice(&output_json).map_err(WasmExecutionError::InvalidOutput)?;
+
+    let output = TaskOutput {
+        task_id: task.id.clone(),
+        result: Entry::Json(result),
+    };
+    Ok((output, store.data_mut().stack_pages.drain().collect()))
+}
+
+fn load_module_bytes(source: &WasmSource) -> Result<Vec<u8>, WasmExecutionError> {
+    match source {
+        WasmSource::Bytes(bytes) => Ok(bytes.clone()),
+        WasmSource::Path(path) => {
+            std::fs::read(path).map_err(|source| WasmExecutionError::ModuleRead {
+                path: path.clone(),
+                source,
+            })
+        }
+    }
+}
+
+fn flatten_inputs(resolved_inputs: HashMap<ID, MemoryInputType>) -> HashMap<ID, Entry> {
+    resolved_inputs
+        .into_iter()
+        .filter_map(|(key, value)| match value {
+            MemoryInputType::Entry(entry) => Some((key, entry)),
+            // The host ABI has no page-reading function; a flattened page only carries its last
+            // entry (the top of the stack), which is what `host_read_entry` returns for that key.
+            MemoryInputType::Page(mut page) => page.pop().map(|entry| (key, entry)),
+        })
+        .collect()
+}
+
+fn host_visible_inputs(memory_entries: &HashMap<ID, Entry>) -> &HashMap<ID, Entry> {
+    memory_entries
+}
+
+/// Registers the host functions the guest ABI exposes: reading a named memory entry and
+/// appending a value to a named stack page.
+fn link_host_functions(linker: &mut Linker<HostState>) -> Result<(), wasmtime::Error> {
+    linker.func_wrap(
+        "env",
+        "host_read_entry",
+        |mut caller: wasmtime::Caller<'_, HostState>, key_ptr: i32, key_len: i32| -> i64 {
+            let Some(key) = read_guest_string(&mut caller, key_ptr, key_len) else {
+                return 0;
+            };
+            let Some(entry) = caller.data().memory_entries.get(&key).cloned() else {
+                return 0;
+            };
+            let Ok(json) = serde_json::to_vec(&entry) else {
+                return 0;
+            };
+            write_guest_bytes(&mut caller, &json).unwrap_or(0)
+        },
+    )?;
+    linker.func_wrap(
+        "env",
+        "host_push_page",
+        |mut caller: wasmtime::Caller<'_, HostState>,
+         page_ptr: i32,
+         page_len: i32,
+         value_ptr: i32,
+         value_len: i32| {
+            if let Some(entry) = read_guest_entry(&mut caller, value_ptr, value_len) {
+                if let Some(page_name) = read_guest_string(&mut caller, page_ptr, page_len) {
+                    caller
+                        .data_mut()
+                        .stack_pages
+                        .entry(page_name)
+                        .or_default()
+                        .push(entry);
+                }
+            }
+        },
+    )?;
+    Ok(())
+}
+
+fn guest_memory(caller: &mut wasmtime::Caller<'_, HostState>) -> Option<Memory> {
+    caller.get_export("memory").and_then(|e| e.into_memory())
+}
+
+fn read_guest_string(
+    caller: &mut wasmtime::Caller<'_, HostState>,
+    ptr: i32,
+    len: i32,
+) -> Option<ID> {
+    let memory = guest_memory(caller)?;
+    let mut buf = vec![0u8; len as usize];
+    memory.read(caller, ptr as usize, &mut buf).ok()?;
+    String::from_utf8(buf).ok()
+}
+
+fn read_guest_entry(
+    caller: &mut wasmtime::Caller<'_, HostState>,
+    ptr: i32,
+    len: i32,
+) -> Option<Entry> {
+    let raw = read_guest_string(caller, ptr, len)?;
+    serde_json::from_str(&raw).ok()
+}
+
+/// Allocates `bytes.len()` bytes in guest memory via the guest's own `alloc` export, writes
+/// `bytes` into it, and packs the result into the same `(ptr << 32 | len)` shape the guest ABI
+/// uses everywhere else a host function hands data back across the boundary.
+fn write_guest_bytes(caller: &mut wasmtime::Caller<'_, HostState>, bytes: &[u8]) -> Option<i64> {
+    let memory = guest_memory(caller)?;
+    let alloc = caller.get_export("alloc")?.into_func()?;
+    let alloc = alloc.typed::<i32, i32>(&caller).ok()?;
+    let ptr = alloc.call(&mut *caller, bytes.len() as i32).ok()?;
+    memory.write(&mut *caller, ptr as usize, bytes).ok()?;
+    Some(((ptr as i64) << 32) | bytes.len() as i64)
+}
+
+/// Writes `input_json` into a fresh linear-memory allocation (via the guest's `alloc` export,
+/// the same pattern used for returning strings across the boundary) and calls `entrypoint` with
+/// its pointer and length, returning the JSON bytes the guest wrote back.
+fn call_entrypoint(
+    store: &mut Store<HostState>,
+    instance: &Instance,
+    entrypoint: &str,
+    input_json: &[u8],
+) -> Result<Vec<u8>, WasmExecutionError> {
+    let entry_fn = instance
+        .get_typed_func::<(i32, i32), i64>(&mut *store, entrypoint)
+        .map_err(|_| WasmExecutionError::MissingEntrypoint(entrypoint.to_string()))?;
+    let alloc_fn = instance
+        .get_typed_func::<i32, i32>(&mut *store, "alloc")
+        .map_err(|_| WasmExecutionError::MissingEntrypoint("alloc".to_string()))?;
+    let memory = instance
+        .get_memory(&mut *store, "memory")
+        .ok_or_else(|| WasmExecutionError::MissingEntrypoint("memory".to_string()))?;
+
+    let ptr = alloc_fn
+        .call(&mut *store, input_json.len() as i32)
+        .map_err(WasmExecutionError::Trap)?;
+    memory
+        .write(&mut *store, ptr as usize, input_json)
+        .map_err(|e| WasmExecutionError::Trap(e.into()))?;
+
+    let packed = entry_fn
+        .call(&mut *store, (ptr, input_json.len() as i32))
+        .map_err(WasmExecutionError::Trap)?;
+    let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+
+    let mut out = vec![0u8; out_len];
+    memory
+        .read(&mut *store, out_ptr, &mut out)
+        .map_err(|e| WasmExecutionError::Trap(e.into()))?;
+    Ok(out)
+}
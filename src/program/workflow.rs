@@ -1,10 +1,25 @@
-use super::atomics::{Config, Edge, Task, TaskOutput};
+use super::atomics::{Config, Edge, Operator, Task, TaskOutput};
+use super::cache::{hash_task, CacheOutcome, InMemoryTaskCache, TaskCache};
+use super::dag::{self, DagError, ExecutionPlan};
+use super::error::WorkflowError;
+use super::snapshot::{self, Manifest, SnapshotError};
+use super::wasm::{execute_wasm_task, WasmExecutionError};
+use crate::memory::conversion::Conversion;
 use crate::memory::types::{self, Entry, MemoryInputType, ID};
 use serde::{Deserialize, Deserializer};
 use serde_json::Value;
 use std::collections::HashMap;
 
-/// Custom deserializer for external memory.
+fn default_cache() -> Box<dyn TaskCache> {
+    Box::new(InMemoryTaskCache::new())
+}
+
+/// Custom deserializer for external memory. Only validates shape (string/object entries, plain
+/// or as a stack page); a `{"value": ..., "convert": ...}` entry is kept as a plain `Entry::Json`
+/// here and only turned into a typed entry by `resolve_conversions`, after the surrounding
+/// `serde_json::from_reader` call has returned -- so a bad conversion can report a structured
+/// `WorkflowError::MemoryError` directly instead of being stringified through `D::Error` and then
+/// re-boxed as a `ParseError` by `new_from_json`.
 fn deserialize_external_memory<'de, D>(
     deserializer: D,
 ) -> Result<Option<HashMap<ID, MemoryInputType>>, D::Error>
@@ -14,9 +29,12 @@ where
     let value: Option<Value> = Option::deserialize(deserializer)?;
 
     if let Some(value) = value {
-        let map = value
-            .as_object()
-            .ok_or_else(|| serde::de::Error::custom("Expected a map"))?;
+        let map = value.as_object().ok_or_else(|| {
+            serde::de::Error::custom(WorkflowError::ParseError {
+                context: "external_memory".to_string(),
+                reason: "expected a JSON object".to_string(),
+            })
+        })?;
 
         let mut external_memory = HashMap::new();
 
@@ -29,7 +47,10 @@ where
                     } else if item.is_object() {
                         stack_page.push(Entry::Json(item.clone()));
                     } else {
-                        return Err(serde::de::Error::custom("Invalid entry format"));
+                        return Err(serde::de::Error::custom(WorkflowError::ParseError {
+                            context: format!("external_memory key `{key}`"),
+                            reason: "invalid entry format in stack page".to_string(),
+                        }));
                     }
                 }
                 external_memory.insert(key.clone(), MemoryInputType::Page(stack_page));
@@ -44,7 +65,10 @@ where
                     MemoryInputType::Entry(Entry::Json(val.clone())),
                 );
             } else {
-                return Err(serde::de::Error::custom("Invalid entry format"));
+                return Err(serde::de::Error::custom(WorkflowError::ParseError {
+                    context: format!("external_memory key `{key}`"),
+                    reason: "invalid entry format".to_string(),
+                }));
             }
         }
 
@@ -54,6 +78,69 @@ where
     }
 }
 
+/// Recognizes the `{"value": "<raw>", "convert": "<conversion name>"}` shape used to annotate a
+/// memory key with a `Conversion`, and applies it. Returns `None` if `val` isn't shaped that way,
+/// so callers can fall back to treating it as a plain JSON entry.
+fn try_convert_entry(key: &str, val: &Value) -> Option<Result<Entry, WorkflowError>> {
+    let obj = val.as_object()?;
+    let raw = obj.get("value")?.as_str()?;
+    let convert = obj.get("convert")?.as_str()?;
+
+    let conversion = match Conversion::parse(convert) {
+        Some(conversion) => conversion,
+        None => {
+            return Some(Err(WorkflowError::MemoryError {
+                key: key.to_string(),
+                detail: format!("unknown conversion `{convert}`"),
+            }))
+        }
+    };
+
+    Some(
+        conversion
+            .apply(key, raw)
+            .map(Entry::Typed)
+            .map_err(|e| WorkflowError::MemoryError {
+                key: key.to_string(),
+                detail: e.to_string(),
+            }),
+    )
+}
+
+/// Walks a freshly-parsed `external_memory`, turning every `{"value", "convert"}`-shaped JSON
+/// entry into a typed one. Run after `serde_json::from_reader` returns, so a `Conversion` failure
+/// surfaces as its own `WorkflowError::MemoryError` rather than being laundered through `D::Error`
+/// and re-wrapped as a `ParseError`.
+fn resolve_conversions(
+    external_memory: &mut Option<HashMap<ID, MemoryInputType>>,
+) -> Result<(), WorkflowError> {
+    let Some(memory) = external_memory.as_mut() else {
+        return Ok(());
+    };
+
+    for (key, value) in memory.iter_mut() {
+        match value {
+            MemoryInputType::Entry(entry) => convert_in_place(key, entry)?,
+            MemoryInputType::Page(page) => {
+                for entry in page.iter_mut() {
+                    convert_in_place(key, entry)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn convert_in_place(key: &str, entry: &mut Entry) -> Result<(), WorkflowError> {
+    if let Entry::Json(json) = entry {
+        if let Some(converted) = try_convert_entry(key, json) {
+            *entry = converted?;
+        }
+    }
+    Ok(())
+}
+
 /// Workflow serves as a container for the tasks and steps that make up a workflow.
 #[derive(Debug, serde::Deserialize)]
 pub struct Workflow {
@@ -63,6 +150,10 @@ pub struct Workflow {
     tasks: Vec<Task>,
     steps: Vec<Edge>,
     return_value: TaskOutput,
+    /// Result cache keyed by a content hash of each task's config-relevant fields and resolved
+    /// inputs. Defaults to an in-memory cache; `with_cache` swaps in another implementation.
+    #[serde(skip, default = "default_cache")]
+    cache: Box<dyn TaskCache>,
 }
 
 impl Workflow {
@@ -79,16 +170,142 @@ impl Workflow {
             tasks,
             steps,
             return_value,
+            cache: default_cache(),
         }
     }
 
+    /// Replaces the result cache with a custom implementation, e.g. a file-backed JSON or
+    /// sqlite store. Defaults to an in-memory `HashMap` if never called.
+    pub fn with_cache(mut self, cache: Box<dyn TaskCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Drops all cached task outputs.
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
     /// Creates a new Workflow from a JSON file.
-    pub fn new_from_json(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let file = std::fs::File::open(path)?;
+    pub fn new_from_json(path: &str) -> Result<Self, WorkflowError> {
+        let file = std::fs::File::open(path).map_err(|err| WorkflowError::ParseError {
+            context: format!("workflow file `{path}`"),
+            reason: err.to_string(),
+        })?;
         let reader = std::io::BufReader::new(file);
-        let workflow: Workflow = serde_json::from_reader(reader)?;
+        let mut workflow: Workflow =
+            serde_json::from_reader(reader).map_err(|err| WorkflowError::ParseError {
+                context: format!("workflow file `{path}`"),
+                reason: err.to_string(),
+            })?;
+        resolve_conversions(&mut workflow.external_memory)?;
+        workflow.validate()?;
         Ok(workflow)
     }
+
+    /// Checks that every edge and the `return_value` reference a task id that actually exists,
+    /// so a dangling reference is caught at load time instead of surfacing as a confusing
+    /// lookup failure mid-execution.
+    pub fn validate(&self) -> Result<(), WorkflowError> {
+        for edge in &self.steps {
+            if self.get_tasks_by_id(&edge.source).is_none() {
+                return Err(WorkflowError::ResolutionError {
+                    task_id: edge.source.clone(),
+                    detail: "referenced as an edge source but not present in tasks".to_string(),
+                });
+            }
+            if self.get_tasks_by_id(&edge.target).is_none() {
+                return Err(WorkflowError::ResolutionError {
+                    task_id: edge.target.clone(),
+                    detail: "referenced as an edge target but not present in tasks".to_string(),
+                });
+            }
+        }
+
+        if self.get_tasks_by_id(&self.return_value.task_id).is_none() {
+            return Err(WorkflowError::ResolutionError {
+                task_id: self.return_value.task_id.clone(),
+                detail: "return_value references a task id that is not present in tasks"
+                    .to_string(),
+            });
+        }
+
+        if self.config.models.is_empty() {
+            return Err(WorkflowError::ConfigError {
+                detail: "models must not be empty".to_string(),
+            });
+        }
+
+        if self.config.max_parallelism == Some(0) {
+            return Err(WorkflowError::ConfigError {
+                detail: "max_parallelism must be at least 1 when set".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Serializes the workflow's full state -- config, tasks, steps, external memory, and the
+    /// return value -- into a gzip-compressed tar archive at `path`, so an expensive multi-step
+    /// pipeline can checkpoint between stages and recover after a crash.
+    pub fn dump(&self, path: impl AsRef<std::path::Path>) -> Result<(), SnapshotError> {
+        let manifest = Manifest {
+            version: snapshot::MANIFEST_VERSION,
+            config: self.config.clone(),
+            tasks: self.tasks.clone(),
+            steps: self.steps.clone(),
+            return_value: self.return_value.clone(),
+            task_ids: self.tasks.iter().map(|task| task.id.clone()).collect(),
+        };
+        snapshot::write_archive(path, &manifest, &self.external_memory)
+    }
+
+    /// Reloads a workflow archive previously written by `dump` into `self`.
+    ///
+    /// Rejects archives from a newer manifest version, and archives whose task set no longer
+    /// matches `self`'s tasks, rather than silently resuming into an inconsistent graph.
+    pub fn restore(&mut self, path: impl AsRef<std::path::Path>) -> Result<(), SnapshotError> {
+        let (manifest, external_memory) = snapshot::read_archive(path)?;
+
+        let mut current_ids: Vec<&str> = self.tasks.iter().map(|task| task.id.as_str()).collect();
+        current_ids.sort_unstable();
+        let mut archived_ids: Vec<&str> = manifest.task_ids.iter().map(String::as_str).collect();
+        archived_ids.sort_unstable();
+        if current_ids != archived_ids {
+            return Err(SnapshotError::TaskSetMismatch);
+        }
+
+        self.config = manifest.config;
+        self.tasks = manifest.tasks;
+        self.steps = manifest.steps;
+        self.return_value = manifest.return_value;
+        self.external_memory = external_memory;
+        Ok(())
+    }
+
+    /// Builds the dependency graph from `steps` and each task's declared inputs, topologically
+    /// sorts it, and groups independent tasks into layers that are safe to run concurrently.
+    pub fn resolve(&self) -> Result<ExecutionPlan, DagError> {
+        dag::resolve(&self.tasks, &self.steps)
+    }
+
+    /// Resolves the workflow into an `ExecutionPlan` and runs it, executing each layer's
+    /// independent tasks concurrently up to `Config::max_parallelism`. `execute` is called once
+    /// per task with no ordering guarantee within a layer, and is handed the outputs of every
+    /// task completed in an earlier layer so a fan-out-then-join task can read what it depends
+    /// on.
+    pub fn run_parallel(
+        &self,
+        execute: impl Fn(&Task, &HashMap<String, TaskOutput>) -> TaskOutput + Sync,
+    ) -> Result<HashMap<String, TaskOutput>, DagError> {
+        let plan = self.resolve()?;
+        Ok(dag::run_parallel(
+            &plan,
+            &self.tasks,
+            self.config.max_parallelism,
+            execute,
+        ))
+    }
 }
 
 impl Workflow {
@@ -128,4 +345,130 @@ impl Workflow {
     pub fn get_tasks_by_id_mut(&mut self, task_id: &str) -> Option<&mut Task> {
         self.tasks.iter_mut().find(|task| task.id == task_id)
     }
+
+    /// Resolves a task's declared inputs to their current values in `external_memory`. Once a
+    /// task has executed, its `TaskOutput` is recorded back into `external_memory` under its own
+    /// task id (see `record_output`), so a downstream task naming an upstream task id in its
+    /// `inputs` resolves to that output here, exactly like naming a plain memory key.
+    fn resolve_inputs(&self, task: &Task) -> Vec<(&ID, &MemoryInputType)> {
+        task.inputs
+            .iter()
+            .filter_map(|key| {
+                self.external_memory
+                    .as_ref()
+                    .and_then(|memory| memory.get(key))
+                    .map(|value| (key, value))
+            })
+            .collect()
+    }
+
+    /// Makes `task_id`'s output visible to downstream tasks' `resolve_inputs` (and therefore to
+    /// their cache hash) by recording it into `external_memory` under the task's own id.
+    fn record_output(&mut self, task_id: &str, output: &TaskOutput) {
+        self.external_memory
+            .get_or_insert_with(HashMap::new)
+            .insert(
+                task_id.to_string(),
+                MemoryInputType::Entry(output.result.clone()),
+            );
+    }
+
+    /// Executes `task`, returning a cached `TaskOutput` on a hit and running `execute` on a miss.
+    ///
+    /// The cache key folds in the task's operator (model + prompt) and the concrete values of
+    /// every input it reads from `external_memory` -- including the outputs of upstream tasks,
+    /// which `record_output` writes back there as each task completes -- so a cached answer is
+    /// reused only when nothing that could affect it has changed. The returned `CacheOutcome`
+    /// tells the caller whether this was a hit or a miss.
+    pub fn execute_task(
+        &mut self,
+        task_id: &str,
+        execute: impl FnOnce(&Task) -> TaskOutput,
+    ) -> Option<CacheOutcome> {
+        let task = self.get_tasks_by_id(task_id)?.clone();
+        let resolved_inputs = self.resolve_inputs(&task);
+        let hash = hash_task(&task, &resolved_inputs);
+
+        let outcome = if let Some(cached) = self.cache.get(hash) {
+            CacheOutcome::Hit(cached)
+        } else {
+            let output = execute(&task);
+            self.cache.insert(hash, output.clone());
+            CacheOutcome::Miss(output)
+        };
+
+        self.record_output(&task.id, match &outcome {
+            CacheOutcome::Hit(output) | CacheOutcome::Miss(output) => output,
+        });
+
+        Some(outcome)
+    }
+
+    /// Runs `task_id`'s `Wasm` operator in a wasmtime sandbox, honoring the cache like
+    /// `execute_task`. Returns `None` if the task doesn't exist or isn't a `Wasm` task.
+    pub fn execute_wasm_step(
+        &mut self,
+        task_id: &str,
+    ) -> Option<Result<CacheOutcome, WasmExecutionError>> {
+        let task = self.get_tasks_by_id(task_id)?.clone();
+        let Operator::Wasm { module, entrypoint } = &task.operator else {
+            return None;
+        };
+
+        let resolved_inputs: HashMap<ID, MemoryInputType> = self
+            .resolve_inputs(&task)
+            .into_iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        let hash = hash_task(&task, &resolved_inputs.iter().collect::<Vec<_>>());
+
+        let outcome = if let Some(cached) = self.cache.get(hash) {
+            CacheOutcome::Hit(cached)
+        } else {
+            match execute_wasm_task(&task, module, entrypoint, resolved_inputs, &self.config) {
+                Ok((output, stack_pages)) => {
+                    self.cache.insert(hash, output.clone());
+                    self.merge_stack_pages(stack_pages);
+                    CacheOutcome::Miss(output)
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        };
+
+        self.record_output(&task.id, match &outcome {
+            CacheOutcome::Hit(output) | CacheOutcome::Miss(output) => output,
+        });
+
+        Some(Ok(outcome))
+    }
+
+    /// Runs `task_id`'s `Wasm` operator like `execute_wasm_step`, but wraps a sandbox failure as
+    /// a `WorkflowError::ExecutionError` so callers that want a single structured error type
+    /// across every execution path don't need to match on `WasmExecutionError` separately.
+    pub fn execute_wasm(&mut self, task_id: &str) -> Option<Result<CacheOutcome, WorkflowError>> {
+        self.execute_wasm_step(task_id).map(|result| {
+            result.map_err(|err| WorkflowError::ExecutionError {
+                task_id: task_id.to_string(),
+                detail: err.to_string(),
+            })
+        })
+    }
+
+    /// Folds stack pages a `Wasm` task appended to via `host_push_page` into `external_memory`,
+    /// appending to any page already present under the same key rather than overwriting it.
+    fn merge_stack_pages(&mut self, stack_pages: HashMap<ID, Vec<Entry>>) {
+        for (key, mut entries) in stack_pages {
+            match self.external_memory.get_or_insert_with(HashMap::new).entry(key) {
+                std::collections::hash_map::Entry::Occupied(mut slot) => match slot.get_mut() {
+                    MemoryInputType::Page(existing) => existing.append(&mut entries),
+                    MemoryInputType::Entry(_) => {
+                        slot.insert(MemoryInputType::Page(entries));
+                    }
+                },
+                std::collections::hash_map::Entry::Vacant(slot) => {
+                    slot.insert(MemoryInputType::Page(entries));
+                }
+            }
+        }
+    }
 }